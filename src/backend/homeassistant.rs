@@ -0,0 +1,33 @@
+//! The Home Assistant [`Backend`], built on top of the reconciling
+//! WebSocket client in [`crate::hass::ws`].
+
+use crate::config::HomeAssistantConfig;
+use crate::hass::ws::WsHandle;
+use crate::hass::APIState;
+
+use super::Backend;
+
+/// Controls a Home Assistant entity.
+pub struct HomeAssistantBackend {
+    ws: WsHandle,
+}
+
+impl HomeAssistantBackend {
+    /// Connect to Home Assistant and start reconciling the configured
+    /// entity's state in the background.
+    pub fn connect(config: HomeAssistantConfig) -> Self {
+        HomeAssistantBackend {
+            ws: WsHandle::connect(config),
+        }
+    }
+}
+
+impl Backend for HomeAssistantBackend {
+    fn set_state(&self, state: APIState) -> Result<(), Box<dyn std::error::Error>> {
+        self.ws.set_state(state)
+    }
+
+    fn get_state(&self) -> Option<APIState> {
+        self.ws.last_known_state()
+    }
+}
@@ -0,0 +1,126 @@
+//! A native HomeKit accessory [`Backend`], for users who do not run Home
+//! Assistant. Publishes a single `Switch` accessory over mDNS using hap-rs
+//! so it can be paired directly from Siri/Home.app, and drives its
+//! characteristic from the same monitor state transitions as the Home
+//! Assistant backend.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use hap::{
+    accessory::{switch::SwitchAccessory, AccessoryCategory, AccessoryInformation},
+    server::{IpServer, Server},
+    storage::FileStorage,
+    Config as HapConfig, Pin,
+};
+
+use crate::config::HomeKitConfig;
+use crate::hass::APIState;
+
+use super::Backend;
+
+/// Name of the directory used to persist HomeKit pairing state, stored next
+/// to `config.toml` so re-pairing isn't needed after every restart. Each
+/// rule gets its own subdirectory so that configuring the HomeKit backend
+/// on more than one rule doesn't collide on pairing state.
+static PAIRING_DIR: &str = "homekit";
+
+/// Drives a HomeKit `Switch` accessory from monitor state transitions.
+pub struct HomeKitBackend {
+    state: Arc<Mutex<APIState>>,
+    /// Handle to the published accessory's `on` characteristic, so
+    /// `set_state` can push a state-changed event to subscribed
+    /// controllers instead of only answering the next read.
+    on_characteristic: Arc<Mutex<hap::characteristic::on::OnCharacteristic>>,
+}
+
+impl HomeKitBackend {
+    /// Publish the accessory and start the HomeKit server in the
+    /// background.
+    pub fn start(
+        config: &HomeKitConfig,
+        config_dir: &Path,
+        rule_id: usize,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let state = Arc::new(Mutex::new(APIState::Off));
+
+        let storage = FileStorage::new(
+            config_dir
+                .join(PAIRING_DIR)
+                .join(rule_id.to_string()),
+        )?;
+        let hap_config = HapConfig {
+            pin: config.pin.parse::<Pin>()?,
+            name: config.name.clone(),
+            category: AccessoryCategory::Switch,
+            ..Default::default()
+        };
+
+        // Accessory ID 1 is reserved for the bridge/root accessory in HAP;
+        // offset by rule so multiple HomeKit-backed rules in one daemon
+        // don't publish colliding instance IDs.
+        let accessory = SwitchAccessory::new(
+            rule_id as u64 + 1,
+            AccessoryInformation {
+                name: config.name.clone(),
+                ..Default::default()
+            },
+        )?;
+
+        let on_characteristic = accessory.switch.on.clone();
+
+        let read_state = state.clone();
+        accessory
+            .switch
+            .on
+            .lock()
+            .unwrap()
+            .on_read_async(move || {
+                let read_state = read_state.clone();
+                async move { Some(*read_state.lock().unwrap() == APIState::On) }
+            });
+
+        // hap-rs is built on tokio, while the rest of the daemon is
+        // synchronous; run the accessory server on its own thread with its
+        // own runtime so the rest of the app never has to think about async.
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().expect("Unable to start HomeKit runtime");
+            runtime.block_on(async move {
+                let server = IpServer::new(hap_config, storage)
+                    .await
+                    .expect("Unable to start HomeKit server");
+                server
+                    .add_accessory(accessory)
+                    .await
+                    .expect("Unable to publish HomeKit accessory");
+
+                tracing::info!("Publishing HomeKit accessory over mDNS");
+                server.run_handle().await.expect("HomeKit server failed");
+            });
+        });
+
+        Ok(HomeKitBackend {
+            state,
+            on_characteristic,
+        })
+    }
+}
+
+impl Backend for HomeKitBackend {
+    fn set_state(&self, state: APIState) -> Result<(), Box<dyn std::error::Error>> {
+        *self.state.lock().unwrap() = state;
+
+        // Push the new value through the characteristic itself (not just
+        // the backing `state`), so HomeKit emits a state-changed event to
+        // any controller already subscribed, instead of only answering the
+        // next read.
+        let mut on = self.on_characteristic.lock().unwrap();
+        futures_lite::future::block_on(on.set_value(serde_json::json!(state == APIState::On)))?;
+
+        Ok(())
+    }
+
+    fn get_state(&self) -> Option<APIState> {
+        Some(*self.state.lock().unwrap())
+    }
+}
@@ -0,0 +1,40 @@
+//! Backends that expose a controllable on/off switch, driven by monitor
+//! state transitions.
+
+use std::path::Path;
+
+use crate::config::BackendConfig;
+use crate::hass::APIState;
+
+pub mod homeassistant;
+pub mod homekit;
+
+/// A backend that exposes a single switch and can report back its last
+/// observed state, so callers can reconcile drift caused by something
+/// outside the daemon flipping it.
+pub trait Backend: Send + Sync {
+    /// Set the desired state for the switch.
+    fn set_state(&self, state: APIState) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Get the last known state of the switch, if one has been observed yet.
+    fn get_state(&self) -> Option<APIState>;
+}
+
+/// Build the [`Backend`] described by a [`BackendConfig`]. `config_dir` is
+/// used by backends that need to persist state next to `config.toml`;
+/// `rule_id` namespaces any such state so multiple rules using the same
+/// backend type don't collide with each other.
+pub fn build_backend(
+    config: &BackendConfig,
+    config_dir: &Path,
+    rule_id: usize,
+) -> Result<Box<dyn Backend>, Box<dyn std::error::Error>> {
+    match config {
+        BackendConfig::HomeAssistant(config) => Ok(Box::new(
+            homeassistant::HomeAssistantBackend::connect(config.clone()),
+        )),
+        BackendConfig::HomeKit(config) => Ok(Box::new(homekit::HomeKitBackend::start(
+            config, config_dir, rule_id,
+        )?)),
+    }
+}
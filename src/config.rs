@@ -11,9 +11,74 @@ static CONFIG_NAME: &str = "config.toml";
 /// Application configuration.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// The process→entity rules to monitor and control.
+    pub rules: Vec<Rule>,
+    /// Sinks to notify whenever a rule's power state changes.
+    #[serde(default)]
+    pub notify: Vec<NotifyConfig>,
+}
+
+/// A single process to monitor paired with the [`crate::backend::Backend`]
+/// it should control.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Rule {
     pub power: PowerConfig,
     pub check: CheckConfig,
-    pub homeassistant: HomeAssistantConfig,
+    pub backend: BackendConfig,
+    /// Commands to run on start/stop transitions, if configured.
+    #[serde(default)]
+    pub hooks: Option<HooksConfig>,
+}
+
+/// Commands to run when a rule's monitor starts or stops.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// Command to run when the monitor transitions to active.
+    pub on_start: Option<String>,
+    /// Command to run once the shutoff delay fires after the monitor goes
+    /// inactive.
+    pub on_stop: Option<String>,
+}
+
+/// Configuration for a [`crate::notify::Notifier`], selected by the `type`
+/// field.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum NotifyConfig {
+    /// Post each event as JSON to a URL.
+    #[serde(rename = "webhook")]
+    Webhook {
+        /// The URL to POST events to.
+        url: String,
+    },
+    /// Send each event as a message through Home Assistant's `notify`
+    /// service.
+    #[serde(rename = "homeassistant")]
+    HomeAssistant(HomeAssistantNotifyConfig),
+}
+
+/// Home Assistant `notify` service configuration.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HomeAssistantNotifyConfig {
+    /// URL of Home Assistant instance.
+    pub url: String,
+    /// API key for Home Assistant instance.
+    pub api_key: String,
+    /// The name of the `notify.<service>` to call, e.g. `mobile_app_phone`.
+    pub service: String,
+}
+
+/// Configuration for a [`crate::backend::Backend`], selected by the `type`
+/// field.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BackendConfig {
+    /// Control an entity through Home Assistant.
+    #[serde(rename = "homeassistant")]
+    HomeAssistant(HomeAssistantConfig),
+    /// Expose a native HomeKit accessory instead of using Home Assistant.
+    #[serde(rename = "homekit")]
+    HomeKit(HomeKitConfig),
 }
 
 /// Home Assistant configuration.
@@ -29,13 +94,48 @@ pub struct HomeAssistantConfig {
     pub entity: String,
 }
 
-/// Processing checking configuration.
+/// HomeKit accessory configuration.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HomeKitConfig {
+    /// The setup pin shown to pair with Siri/Home.app, e.g. `"11122333"`.
+    pub pin: String,
+    /// The name of the published `Switch` accessory.
+    pub name: String,
+}
+
+/// Configuration for a [`crate::monitor::Monitor`] backend, selected by the
+/// `type` field.
 #[derive(Clone, Serialize, Deserialize)]
-pub struct CheckConfig {
-    /// The name of the process to monitor.
-    pub process_name: String,
-    /// The interval to check for the process.
-    pub interval: u64,
+#[serde(tag = "type")]
+pub enum CheckConfig {
+    /// Check for a process with the given name.
+    #[serde(rename = "process")]
+    Process {
+        /// The name of the process to monitor.
+        process_name: String,
+        /// The interval to check for the process.
+        interval: u64,
+    },
+    /// Check whether something is listening on a local TCP port.
+    #[serde(rename = "tcp_port")]
+    TcpPort {
+        /// The local port to check.
+        port: u16,
+        /// The interval to check the port.
+        interval: u64,
+    },
+    /// Check whether a process has sustained CPU usage over a threshold.
+    #[serde(rename = "cpu_threshold")]
+    CpuThreshold {
+        /// The name of the process to monitor.
+        process_name: String,
+        /// The CPU usage percentage that counts as active.
+        threshold: f32,
+        /// How long usage must stay over the threshold before becoming active.
+        sustained_for: u64,
+        /// The interval to check the process.
+        interval: u64,
+    },
 }
 
 /// Power control configuration.
@@ -93,31 +193,35 @@ pub fn prompt_config(config_dir: &std::path::Path) -> Result<Config, Box<dyn std
         stdout.flush().unwrap();
         stdin.read_line(&mut entity)?;
 
-        let config = Config {
-            power: PowerConfig { delay: 60 },
-            check: CheckConfig {
-                process_name: "vrserver.exe".to_string(),
-                interval: 3,
-            },
-            homeassistant: HomeAssistantConfig {
-                url: url.trim().to_string(),
-                api_key: api_key.trim().to_string(),
-                service: "switch".to_string(),
-                entity: entity.trim().to_string(),
-            },
+        let homeassistant = HomeAssistantConfig {
+            url: url.trim().to_string(),
+            api_key: api_key.trim().to_string(),
+            service: "switch".to_string(),
+            entity: entity.trim().to_string(),
         };
 
-        if !check_credentials(&config.homeassistant) {
+        if !check_credentials(&homeassistant) {
             eprintln!("Home Assistant credentials were invalid, please try again");
             continue;
         }
 
-        if get_entity_state(&config.homeassistant).is_err() {
+        if get_entity_state(&homeassistant).is_err() {
             eprintln!("Home Assistant entity returned error, please try again");
             continue;
         }
 
-        break config;
+        break Config {
+            rules: vec![Rule {
+                power: PowerConfig { delay: 60 },
+                check: CheckConfig::Process {
+                    process_name: "vrserver.exe".to_string(),
+                    interval: 3,
+                },
+                backend: BackendConfig::HomeAssistant(homeassistant),
+                hooks: None,
+            }],
+            notify: Vec::new(),
+        };
     };
 
     save_config(config_dir, &config)?;
@@ -2,10 +2,12 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::config::HomeAssistantConfig;
+use crate::config::{HomeAssistantConfig, HomeAssistantNotifyConfig};
+
+pub mod ws;
 
 /// An on or off value for Home Assistant.
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum APIState {
     On,
@@ -26,6 +28,13 @@ struct APIServiceCall {
     entity_id: String,
 }
 
+/// The body needed to call a `notify` service.
+#[derive(Debug, Serialize)]
+struct APINotifyCall<'a> {
+    /// The message to send.
+    message: &'a str,
+}
+
 /// Check the provided credentials by ensuring a valid API response is received.
 pub fn check_credentials(config: &HomeAssistantConfig) -> bool {
     ureq::get(&format!("{}/api/", config.url))
@@ -70,3 +79,18 @@ pub fn set_entity_state(
 
     Ok(())
 }
+
+/// Call a Home Assistant `notify.<service>` service with a message.
+pub fn send_notification(
+    config: &HomeAssistantNotifyConfig,
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    ureq::post(&format!(
+        "{}/api/services/notify/{}",
+        config.url, config.service
+    ))
+    .set("Authorization", &format!("Bearer {}", config.api_key))
+    .send_json(serde_json::to_value(APINotifyCall { message }).unwrap())?;
+
+    Ok(())
+}
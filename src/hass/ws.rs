@@ -0,0 +1,232 @@
+//! Live Home Assistant state reconciliation over the WebSocket API.
+//!
+//! The REST calls in [`crate::hass`] are one-shot pokes: if someone flips the
+//! switch from the Home Assistant app, the daemon never notices and the real
+//! device can drift out of sync with the monitored process. This module
+//! authenticates over the WebSocket API, subscribes to `state_changed` for
+//! the configured entity, and keeps the last-known state in memory so the
+//! caller can re-assert the desired state whenever it drifts. If the
+//! connection drops, [`WsHandle::set_state`] falls back to the REST API
+//! until the connection is re-established.
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::json;
+use tungstenite::Message;
+
+use crate::config::HomeAssistantConfig;
+
+use super::{set_entity_state, APIServiceCall, APIState, APIStateResponse};
+
+/// How long to wait before retrying a dropped WebSocket connection.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// How often to check for outgoing commands and incoming messages while
+/// connected.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A handle to a Home Assistant WebSocket connection running in the
+/// background, reconciling the configured entity's state.
+pub struct WsHandle {
+    config: HomeAssistantConfig,
+    last_known: Arc<Mutex<Option<APIState>>>,
+    connected: Arc<Mutex<bool>>,
+    command_tx: mpsc::Sender<APIState>,
+}
+
+impl WsHandle {
+    /// Connect to Home Assistant's WebSocket API and start reconciling the
+    /// configured entity's state in the background.
+    pub fn connect(config: HomeAssistantConfig) -> Self {
+        let last_known = Arc::new(Mutex::new(None));
+        let connected = Arc::new(Mutex::new(false));
+        let (command_tx, command_rx) = mpsc::channel();
+
+        {
+            let config = config.clone();
+            let last_known = last_known.clone();
+            let connected = connected.clone();
+            std::thread::spawn(move || run(config, last_known, connected, command_rx));
+        }
+
+        WsHandle {
+            config,
+            last_known,
+            connected,
+            command_tx,
+        }
+    }
+
+    /// The last state observed for the entity over the WebSocket connection,
+    /// if any has been received yet.
+    pub fn last_known_state(&self) -> Option<APIState> {
+        *self.last_known.lock().unwrap()
+    }
+
+    /// Set the desired state for the entity. Sent over the WebSocket
+    /// connection if it is currently up, otherwise falls back to a one-shot
+    /// REST call.
+    pub fn set_state(&self, state: APIState) -> Result<(), Box<dyn std::error::Error>> {
+        if *self.connected.lock().unwrap() {
+            self.command_tx.send(state)?;
+            Ok(())
+        } else {
+            tracing::debug!("WebSocket not connected, falling back to REST");
+            set_entity_state(&self.config, state)
+        }
+    }
+}
+
+/// An incoming message from Home Assistant's WebSocket API. Only the fields
+/// needed to drive authentication and state reconciliation are modeled.
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum IncomingMessage {
+    AuthRequired,
+    AuthOk,
+    AuthInvalid,
+    Event { event: StateChangedEvent },
+    #[serde(other)]
+    Other,
+}
+
+/// The `state_changed` event payload.
+#[derive(Debug, serde::Deserialize)]
+struct StateChangedEvent {
+    data: StateChangedData,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct StateChangedData {
+    entity_id: String,
+    new_state: Option<APIStateResponse>,
+}
+
+/// Connect and reconnect forever, feeding observed state changes into
+/// `last_known` and applying outgoing commands from `command_rx`.
+fn run(
+    config: HomeAssistantConfig,
+    last_known: Arc<Mutex<Option<APIState>>>,
+    connected: Arc<Mutex<bool>>,
+    command_rx: mpsc::Receiver<APIState>,
+) {
+    loop {
+        if let Err(err) = connect_and_run(&config, &last_known, &connected, &command_rx) {
+            tracing::warn!(?err, "WebSocket connection to Home Assistant failed");
+        }
+
+        *connected.lock().unwrap() = false;
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
+
+/// Run a single WebSocket session: authenticate, subscribe to
+/// `state_changed`, then alternate between reading incoming events and
+/// sending any queued outgoing commands until the connection fails.
+fn connect_and_run(
+    config: &HomeAssistantConfig,
+    last_known: &Arc<Mutex<Option<APIState>>>,
+    connected: &Arc<Mutex<bool>>,
+    command_rx: &mpsc::Receiver<APIState>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ws_url = url::Url::parse(&format!(
+        "{}/api/websocket",
+        config.url.replacen("http", "ws", 1)
+    ))?;
+
+    // Connect the TCP stream ourselves so we can set a read timeout, which
+    // lets the loop below poll for outgoing commands between reads instead
+    // of blocking on the socket forever.
+    let host = ws_url.host_str().ok_or("websocket URL has no host")?;
+    let port = ws_url.port_or_known_default().unwrap_or(8123);
+    let tcp = std::net::TcpStream::connect((host, port))?;
+    tcp.set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let (mut socket, _) = tungstenite::client(ws_url, tcp)?;
+
+    // Home Assistant sends `auth_required` first, then expects an `auth`
+    // message containing the bearer token before anything else is accepted.
+    expect_message(&mut socket, "auth_required")?;
+    socket.write_message(Message::Text(
+        json!({ "type": "auth", "access_token": config.api_key }).to_string(),
+    ))?;
+    expect_message(&mut socket, "auth_ok")?;
+
+    socket.write_message(Message::Text(
+        json!({ "id": 1, "type": "subscribe_events", "event_type": "state_changed" }).to_string(),
+    ))?;
+
+    *connected.lock().unwrap() = true;
+    tracing::info!("Connected to Home Assistant WebSocket API");
+
+    let mut next_id = 2;
+
+    loop {
+        match socket.read_message() {
+            Ok(Message::Text(text)) => {
+                if let Ok(IncomingMessage::Event { event }) = serde_json::from_str(&text) {
+                    if event.data.entity_id == config.entity {
+                        let state = event.data.new_state.map(|s| s.state);
+                        *last_known.lock().unwrap() = state;
+                        tracing::debug!(?state, "Observed entity state over WebSocket");
+                    }
+                }
+            }
+            Ok(Message::Close(_)) => return Err("connection closed by Home Assistant".into()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err)) if matches!(err.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        while let Ok(state) = command_rx.try_recv() {
+            let service = match state {
+                APIState::On => "turn_on",
+                APIState::Off => "turn_off",
+            };
+
+            socket.write_message(Message::Text(
+                json!({
+                    "id": next_id,
+                    "type": "call_service",
+                    "domain": config.service,
+                    "service": service,
+                    "service_data": APIServiceCall { entity_id: config.entity.clone() },
+                })
+                .to_string(),
+            ))?;
+            next_id += 1;
+        }
+    }
+}
+
+/// Read the next message and ensure its `type` matches `expected`, bailing
+/// out with an error otherwise (including on `auth_invalid`).
+fn expect_message<S: std::io::Read + std::io::Write>(
+    socket: &mut tungstenite::WebSocket<S>,
+    expected: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let text = loop {
+        match socket.read_message() {
+            Ok(Message::Text(text)) => break text,
+            Ok(_) => continue,
+            Err(tungstenite::Error::Io(err))
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+
+    let message: IncomingMessage = serde_json::from_str(&text)?;
+    match (expected, &message) {
+        ("auth_required", IncomingMessage::AuthRequired) => Ok(()),
+        ("auth_ok", IncomingMessage::AuthOk) => Ok(()),
+        (_, IncomingMessage::AuthInvalid) => Err("Home Assistant rejected the access token".into()),
+        _ => Err(format!("expected {expected}, got {message:?}").into()),
+    }
+}
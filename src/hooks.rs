@@ -0,0 +1,96 @@
+//! Run user-defined commands on a rule's start/stop transitions.
+
+use async_process::{Command, Stdio};
+
+use crate::config::HooksConfig;
+
+/// Context made available to a hook command as `VR_*` environment
+/// variables.
+pub struct HookVars {
+    /// The index of the rule that triggered the hook.
+    pub rule_id: usize,
+    /// The name of the process being monitored, if this rule uses a
+    /// process-based check.
+    pub process_name: Option<String>,
+    /// The pid of the monitored process, if the monitor tracks one and it
+    /// was active.
+    pub pid: Option<usize>,
+}
+
+/// Spawn a rule's `on_start` hook, if configured.
+pub fn run_on_start(hooks: &HooksConfig, vars: HookVars) {
+    if let Some(command) = &hooks.on_start {
+        spawn_hook(command.clone(), "active", vars);
+    }
+}
+
+/// Spawn a rule's `on_stop` hook, if configured.
+pub fn run_on_stop(hooks: &HooksConfig, vars: HookVars) {
+    if let Some(command) = &hooks.on_stop {
+        spawn_hook(command.clone(), "inactive", vars);
+    }
+}
+
+/// Spawn `command` on its own thread so a slow or hanging hook can never
+/// stall the poll loop. Captures stdout/stderr into `tracing` at debug
+/// level; a nonzero exit is logged but does not crash the daemon.
+fn spawn_hook(command: String, state: &'static str, vars: HookVars) {
+    std::thread::spawn(move || {
+        let rule_id = vars.rule_id;
+
+        if let Err(err) = futures_lite::future::block_on(run(command, state, vars)) {
+            tracing::warn!(rule_id, ?err, "Unable to run hook command");
+        }
+    });
+}
+
+async fn run(
+    command: String,
+    state: &'static str,
+    vars: HookVars,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = shell_command(&command);
+
+    cmd.env("VR_STATE", state)
+        .env("VR_RULE_ID", vars.rule_id.to_string())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if let Some(process_name) = vars.process_name {
+        cmd.env("VR_PROCESS_NAME", process_name);
+    }
+
+    if let Some(pid) = vars.pid {
+        cmd.env("VR_PID", pid.to_string());
+    }
+
+    let output = cmd.output().await?;
+
+    if !output.stdout.is_empty() {
+        tracing::debug!(stdout = %String::from_utf8_lossy(&output.stdout), "Hook stdout");
+    }
+
+    if !output.stderr.is_empty() {
+        tracing::debug!(stderr = %String::from_utf8_lossy(&output.stderr), "Hook stderr");
+    }
+
+    if !output.status.success() {
+        tracing::warn!(status = ?output.status, command, "Hook command exited with a nonzero status");
+    }
+
+    Ok(())
+}
+
+/// Wrap `command` in the platform's shell so users can write the same kind
+/// of command line they'd type themselves.
+fn shell_command(command: &str) -> Command {
+    if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
@@ -2,94 +2,144 @@
 //! entity based on the state.
 
 use std::sync::{mpsc, Arc, Mutex};
-use sysinfo::{ProcessExt, SystemExt};
+use std::time::Duration;
+use sysinfo::SystemExt;
 
+mod backend;
 mod config;
 mod hass;
+mod hooks;
+mod monitor;
+mod notify;
+mod retry;
 
+use backend::Backend;
 use config::CheckConfig;
-use hass::{set_entity_state, APIState};
-
-/// A thread-safe mutable [`sysinfo::System`].
-type SystemInfo = Arc<Mutex<sysinfo::System>>;
-
-/// The current state of the VR process. When running, it includes the pid.
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum VRState {
-    /// The process is not running.
-    NotRunning,
-    /// The process is running and has the specified pid.
-    Running(usize),
-}
+use hass::APIState;
+use hooks::HookVars;
+use monitor::{Monitor, MonitorState};
+use notify::{Notifier, PowerEvent};
+use retry::RetryState;
 
-/// Get the state of the VR process, refreshing data and lookup up the process
-/// by name.
-fn get_initial_state(config: &CheckConfig, system_info: SystemInfo) -> VRState {
-    let mut system = system_info.lock().unwrap();
-    system.refresh_processes();
+/// A thread-safe mutable [`sysinfo::System`], shared across monitors so
+/// repeated polls coalesce into one process-table refresh; see
+/// [`monitor::SharedSystem`].
+type SystemInfo = Arc<Mutex<monitor::SharedSystem>>;
 
-    match system.get_process_by_name(&config.process_name).first() {
-        Some(process) => VRState::Running(process.pid()),
-        None => VRState::NotRunning,
-    }
-}
+/// How often to re-check a rule's entity even if nothing woke the applier,
+/// to catch state that drifted due to something outside the daemon.
+const RECONCILE_INTERVAL: Duration = Duration::from_secs(30);
 
-/// Check the state of the VR process, refreshing data as frequently as
-/// specified in the configuration. Events are only sent on changes. It returns
-/// a tuple containing the current state and if the value is the initial value.
-fn poll_vr_state_updates(
-    config: CheckConfig,
-    system_info: SystemInfo,
-) -> mpsc::Receiver<(VRState, bool)> {
-    let (tx, rx) = mpsc::channel();
+/// An update from a rule's poller thread, or a delayed shutoff timer firing.
+/// Tagging each variant with `rule_id` lets a single loop multiplex updates
+/// from every configured rule.
+enum Event {
+    /// A rule's monitor changed state.
+    State {
+        rule_id: usize,
+        state: MonitorState,
+        initial: bool,
+        pid: Option<usize>,
+    },
+    /// A rule's shutoff delay has elapsed. `generation` is compared against
+    /// the rule's latest generation to discard stale timers superseded by a
+    /// more recent state change.
+    Timeout { rule_id: usize, generation: u64 },
+}
 
+/// Poll a rule's [`Monitor`] on its own interval. Events are only sent on
+/// changes, tagged with `rule_id` and sent into the shared `tx` so many
+/// pollers can be multiplexed into a single select loop.
+fn poll_vr_state_updates(rule_id: usize, mut monitor: Box<dyn Monitor>, tx: mpsc::Sender<Event>) {
     std::thread::spawn(move || {
         // Get initial state to initialize values and inform program what the
         // value was before starting the program.
-        let mut state = get_initial_state(&config, system_info.clone());
-        tracing::info!(?state, "Got initial state");
-        tx.send((state, true)).unwrap();
+        let mut state = monitor.poll();
+        tracing::info!(rule_id, ?state, "Got initial state");
+        tx.send(Event::State {
+            rule_id,
+            state,
+            initial: true,
+            pid: monitor.pid(),
+        })
+        .unwrap();
 
-        // Loop forever, checking the state of the VR process.
+        // Loop forever, checking the state of the monitor.
         loop {
+            std::thread::sleep(monitor.interval());
+
             let old_state = state;
+            state = monitor.poll();
 
-            let mut system = system_info.lock().unwrap();
-            system.refresh_processes();
-
-            // If the process was not previously running, we need to look up the
-            // process by name because we do not know the pid. If we have the
-            // pid we can lookup the process by that instead.
-            match state {
-                VRState::NotRunning => {
-                    if let Some(process) = system.get_process_by_name(&config.process_name).first()
-                    {
-                        state = VRState::Running(process.pid())
-                    }
-                }
+            tracing::trace!(rule_id, ?state, ?old_state, "Updated state");
 
-                VRState::Running(pid) => {
-                    if system.get_process(pid).is_none() {
-                        state = VRState::NotRunning
-                    }
-                }
+            // Only send updates on changes.
+            if state != old_state {
+                tracing::debug!(rule_id, ?state, "Got new state");
+                tx.send(Event::State {
+                    rule_id,
+                    state,
+                    initial: false,
+                    pid: monitor.pid(),
+                })
+                .unwrap();
             }
+        }
+    });
+}
+
+/// The process name a rule's check is monitoring, if it is a process-based
+/// check. Used to populate `VR_PROCESS_NAME` for hooks.
+fn process_name(check: &CheckConfig) -> Option<String> {
+    match check {
+        CheckConfig::Process { process_name, .. }
+        | CheckConfig::CpuThreshold { process_name, .. } => Some(process_name.clone()),
+        CheckConfig::TcpPort { .. } => None,
+    }
+}
 
-            drop(system);
+/// Continuously apply a rule's desired state to its backend. Wakes up
+/// whenever `wake_rx` is notified (the desired state changed), the current
+/// backoff's next attempt comes due, or every [`RECONCILE_INTERVAL`] (to
+/// catch drift from something outside the daemon), whichever comes first.
+/// `desired` is the single source of truth:
+/// a failed call is retried with backoff rather than queued, so once the
+/// backend recovers the daemon applies whatever is current, collapsing any
+/// transitions that were missed in between into one corrective call.
+fn apply_desired_state(
+    backend: Arc<dyn Backend>,
+    desired: Arc<Mutex<APIState>>,
+    wake_rx: mpsc::Receiver<()>,
+) {
+    std::thread::spawn(move || {
+        let mut retry = RetryState::default();
 
-            tracing::trace!(?state, ?old_state, "Updated state");
+        loop {
+            let wait = retry.time_until_ready().min(RECONCILE_INTERVAL);
+            let _ = wake_rx.recv_timeout(wait);
 
-            // Only send updates on changes.
-            if state != old_state {
-                tracing::debug!(?state, "Got new state");
-                tx.send((state, false)).unwrap();
+            if !retry.is_ready() {
+                continue;
+            }
+
+            let desired = *desired.lock().unwrap();
+            if backend.get_state() == Some(desired) {
+                retry.record_success();
+                continue;
             }
 
-            std::thread::sleep(std::time::Duration::from_secs(config.interval));
+            match backend.set_state(desired) {
+                Ok(()) => {
+                    tracing::debug!(?desired, "Applied desired entity state");
+                    retry.record_success();
+                }
+                Err(err) => {
+                    tracing::warn!(?err, ?desired, "Unable to apply entity state, will retry");
+                    retry.record_failure();
+                }
+            }
         }
     });
-
-    rx
 }
 
 fn main() {
@@ -120,48 +170,145 @@ fn main() {
         }
     };
 
-    let system_info = Arc::new(Mutex::new(sysinfo::System::new_with_specifics(
-        sysinfo::RefreshKind::new().with_processes(),
+    let system_info = Arc::new(Mutex::new(monitor::SharedSystem::new(
+        sysinfo::System::new_with_specifics(sysinfo::RefreshKind::new().with_processes()),
     )));
 
-    let updates = poll_vr_state_updates(config.check.clone(), system_info);
+    let notifiers: Arc<Vec<Box<dyn Notifier>>> = Arc::new(notify::build_notifiers(&config.notify));
+
+    let (tx, rx) = mpsc::channel();
+
+    // Build a backend per rule, each applying its own desired state with
+    // retries, and track that desired state so drift can be detected and
+    // corrected.
+    let backends: Vec<Arc<dyn Backend>> = config
+        .rules
+        .iter()
+        .enumerate()
+        .map(|(rule_id, rule)| {
+            backend::build_backend(&rule.backend, config_dir, rule_id)
+                .expect("Unable to start backend")
+                .into()
+        })
+        .collect();
+    let desired_states: Vec<_> = config
+        .rules
+        .iter()
+        .map(|_| Arc::new(Mutex::new(APIState::Off)))
+        .collect();
+
+    let wake_txs: Vec<_> = backends
+        .iter()
+        .cloned()
+        .zip(desired_states.iter().cloned())
+        .map(|(backend, desired)| {
+            let (wake_tx, wake_rx) = mpsc::channel();
+            apply_desired_state(backend, desired, wake_rx);
+            wake_tx
+        })
+        .collect();
+
+    // Spawn one poller thread per rule, sharing the same [`SystemInfo`] so
+    // concurrent polls coalesce into one process-table refresh rather than
+    // one per rule (see [`monitor::SharedSystem`]).
+    for (rule_id, rule) in config.rules.iter().enumerate() {
+        let monitor = monitor::build_monitor(&rule.check, system_info.clone());
+        poll_vr_state_updates(rule_id, monitor, tx.clone());
+    }
+
+    // Tracks the current generation of each rule's pending shutoff timer, so
+    // a state change can invalidate a timer that has not fired yet.
+    let mut generations = vec![0u64; config.rules.len()];
 
     loop {
-        // Wait for the next state, blocking until a value is available. There
-        // will always be an initial value available to ensure the current
-        // device state is correct.
-        let state = updates.recv().unwrap();
-        tracing::info!(?state, "VR state update");
-
-        match state {
-            // If the state has changed to running, turn on the entity. It does
-            // not matter if this was an initial value or not.
-            (VRState::Running(_pid), _) => set_entity_state(&config.homeassistant, APIState::On)
-                .expect("Unable to turn entity on"),
-            // If VR is not running and this is the initial state, ensure the
-            // devices are off.
-            (VRState::NotRunning, true) => set_entity_state(&config.homeassistant, APIState::Off)
-                .expect("Unable to turn entity off"),
-            // If VR is not running and this is not the initial value, wait for
-            // up to some number seconds for a new state to come in before
-            // turning the devices off.
-            (VRState::NotRunning, false) => {
-                tracing::debug!(
-                    delay = config.power.delay,
-                    "Waiting to ensure software is not being restarted"
-                );
-
-                // If we get a new value that is still not running (this should
-                // not be possible) or we have a timeout, turn off devices.
-                // Otherwise, the new value suggests things are running again
-                // and devices should not be turned off.
-                match updates.recv_timeout(std::time::Duration::from_secs(config.power.delay)) {
-                    Ok((VRState::NotRunning, _)) | Err(_) => {
-                        tracing::info!("Turning off devices");
-                        set_entity_state(&config.homeassistant, APIState::Off)
-                            .expect("Unable to turn entity off");
+        // Wait for the next event, blocking until a value is available. There
+        // will always be an initial value available per rule to ensure the
+        // current device states are correct.
+        let event = rx.recv().unwrap();
+
+        match event {
+            Event::State {
+                rule_id,
+                state,
+                initial,
+                pid,
+            } => {
+                tracing::info!(rule_id, ?state, "VR state update");
+                let rule = &config.rules[rule_id];
+
+                match (state, initial) {
+                    // If the state has changed to active, turn on the entity
+                    // and cancel any pending shutoff. It does not matter if
+                    // this was an initial value or not.
+                    (MonitorState::Active, _) => {
+                        generations[rule_id] += 1;
+                        *desired_states[rule_id].lock().unwrap() = APIState::On;
+                        wake_txs[rule_id].send(()).ok();
+                        notify::dispatch(&notifiers, PowerEvent::TurnedOn { rule_id });
+
+                        if let Some(hooks) = &rule.hooks {
+                            hooks::run_on_start(
+                                hooks,
+                                HookVars {
+                                    rule_id,
+                                    process_name: process_name(&rule.check),
+                                    pid,
+                                },
+                            );
+                        }
+                    }
+                    // If the monitor is inactive and this is the initial
+                    // state, ensure the devices are off.
+                    (MonitorState::Inactive, true) => {
+                        *desired_states[rule_id].lock().unwrap() = APIState::Off;
+                        wake_txs[rule_id].send(()).ok();
+                    }
+                    // If the monitor is inactive and this is not the initial
+                    // value, wait for up to some number of seconds for a new
+                    // state to come in before turning the devices off.
+                    (MonitorState::Inactive, false) => {
+                        generations[rule_id] += 1;
+                        let generation = generations[rule_id];
+
+                        tracing::debug!(
+                            rule_id,
+                            delay = rule.power.delay,
+                            "Waiting to ensure software is not being restarted"
+                        );
+
+                        let tx = tx.clone();
+                        let delay = rule.power.delay;
+                        std::thread::spawn(move || {
+                            std::thread::sleep(Duration::from_secs(delay));
+                            tx.send(Event::Timeout { rule_id, generation }).ok();
+                        });
+                    }
+                }
+            }
+
+            // If the generation has not changed since the timer was started,
+            // no newer state update superseded it, so turn off the devices.
+            Event::Timeout { rule_id, generation } => {
+                if generations[rule_id] == generation {
+                    tracing::info!(rule_id, "Turning off devices");
+                    let rule = &config.rules[rule_id];
+                    *desired_states[rule_id].lock().unwrap() = APIState::Off;
+                    wake_txs[rule_id].send(()).ok();
+                    notify::dispatch(&notifiers, PowerEvent::TurnedOff { rule_id });
+
+                    if let Some(hooks) = &rule.hooks {
+                        hooks::run_on_stop(
+                            hooks,
+                            HookVars {
+                                rule_id,
+                                process_name: process_name(&rule.check),
+                                pid: None,
+                            },
+                        );
                     }
-                    _ => tracing::info!("Did not need to turn off devices"),
+                } else {
+                    tracing::info!(rule_id, "Did not need to turn off devices");
+                    notify::dispatch(&notifiers, PowerEvent::ShutoffSkipped { rule_id });
                 }
             }
         }
@@ -0,0 +1,219 @@
+//! Pluggable monitor backends. Each [`Monitor`] polls some external
+//! condition on its own interval and reports whether it is active.
+
+use std::net::TcpStream;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+
+use sysinfo::{ProcessExt, SystemExt};
+
+use crate::config::CheckConfig;
+use crate::SystemInfo;
+
+/// How long a process-table refresh is considered fresh enough to reuse.
+/// Several monitors share one [`SharedSystem`] and poll independently, so
+/// without this, N process-based rules would each trigger their own
+/// `refresh_processes()` call; this coalesces those into one per tick.
+const REFRESH_COALESCE_WINDOW: Duration = Duration::from_millis(500);
+
+/// A [`sysinfo::System`] paired with the time it was last refreshed, shared
+/// by every monitor as [`crate::SystemInfo`] so that `refresh_processes`
+/// rescans the process table at most once per [`REFRESH_COALESCE_WINDOW`]
+/// no matter how many monitors poll within it.
+pub struct SharedSystem {
+    system: sysinfo::System,
+    last_refresh: Option<Instant>,
+}
+
+impl SharedSystem {
+    /// Wrap a [`sysinfo::System`] for sharing across monitors.
+    pub fn new(system: sysinfo::System) -> Self {
+        SharedSystem {
+            system,
+            last_refresh: None,
+        }
+    }
+
+    /// Refresh the process table, skipping the rescan if another monitor
+    /// already refreshed it within [`REFRESH_COALESCE_WINDOW`].
+    pub fn refresh_processes(&mut self) {
+        let fresh = self
+            .last_refresh
+            .map_or(false, |at| at.elapsed() < REFRESH_COALESCE_WINDOW);
+        if fresh {
+            return;
+        }
+
+        self.system.refresh_processes();
+        self.last_refresh = Some(Instant::now());
+    }
+}
+
+impl Deref for SharedSystem {
+    type Target = sysinfo::System;
+
+    fn deref(&self) -> &sysinfo::System {
+        &self.system
+    }
+}
+
+/// Whether a monitor's condition is currently active or inactive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MonitorState {
+    /// The condition is currently active, e.g. the process is running.
+    Active,
+    /// The condition is not currently active.
+    Inactive,
+}
+
+/// A pluggable source of [`MonitorState`], polled on its own interval.
+pub trait Monitor: Send {
+    /// Check the current state, refreshing any data it depends on.
+    fn poll(&mut self) -> MonitorState;
+
+    /// How often this monitor should be polled.
+    fn interval(&self) -> Duration;
+
+    /// The pid of the process backing the last [`MonitorState::Active`]
+    /// result, if this monitor tracks one. Used to populate `VR_PID` for
+    /// hooks; monitors that don't track a single process return `None`.
+    fn pid(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Build the [`Monitor`] described by a [`CheckConfig`].
+pub fn build_monitor(config: &CheckConfig, system_info: SystemInfo) -> Box<dyn Monitor> {
+    match config.clone() {
+        CheckConfig::Process {
+            process_name,
+            interval,
+        } => Box::new(ProcessMonitor {
+            process_name,
+            interval: Duration::from_secs(interval),
+            system_info,
+            last_pid: None,
+        }),
+
+        CheckConfig::TcpPort { port, interval } => Box::new(TcpPortMonitor {
+            port,
+            interval: Duration::from_secs(interval),
+        }),
+
+        CheckConfig::CpuThreshold {
+            process_name,
+            threshold,
+            sustained_for,
+            interval,
+        } => Box::new(CpuThresholdMonitor {
+            process_name,
+            threshold,
+            sustained_for: Duration::from_secs(sustained_for),
+            interval: Duration::from_secs(interval),
+            system_info,
+            over_since: None,
+            last_pid: None,
+        }),
+    }
+}
+
+/// Monitors whether a process with a given name is running.
+struct ProcessMonitor {
+    process_name: String,
+    interval: Duration,
+    system_info: SystemInfo,
+    last_pid: Option<usize>,
+}
+
+impl Monitor for ProcessMonitor {
+    fn poll(&mut self) -> MonitorState {
+        let mut system = self.system_info.lock().unwrap();
+        system.refresh_processes();
+
+        match system.get_process_by_name(&self.process_name).first() {
+            Some(process) => {
+                self.last_pid = Some(process.pid());
+                MonitorState::Active
+            }
+            None => {
+                self.last_pid = None;
+                MonitorState::Inactive
+            }
+        }
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn pid(&self) -> Option<usize> {
+        self.last_pid
+    }
+}
+
+/// Monitors whether something is listening on a local TCP port, e.g.
+/// SteamVR's web server.
+struct TcpPortMonitor {
+    port: u16,
+    interval: Duration,
+}
+
+impl Monitor for TcpPortMonitor {
+    fn poll(&mut self) -> MonitorState {
+        match TcpStream::connect(("127.0.0.1", self.port)) {
+            Ok(_) => MonitorState::Active,
+            Err(_) => MonitorState::Inactive,
+        }
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+/// Monitors whether a process has had CPU usage over a threshold for at
+/// least `sustained_for`, to avoid reacting to brief spikes.
+struct CpuThresholdMonitor {
+    process_name: String,
+    threshold: f32,
+    sustained_for: Duration,
+    interval: Duration,
+    system_info: SystemInfo,
+    over_since: Option<Instant>,
+    last_pid: Option<usize>,
+}
+
+impl Monitor for CpuThresholdMonitor {
+    fn poll(&mut self) -> MonitorState {
+        let mut system = self.system_info.lock().unwrap();
+        system.refresh_processes();
+
+        let processes = system.get_process_by_name(&self.process_name);
+        self.last_pid = processes.first().map(|process| process.pid());
+        let over_threshold = processes
+            .iter()
+            .any(|process| process.cpu_usage() >= self.threshold);
+
+        drop(system);
+
+        if !over_threshold {
+            self.over_since = None;
+            return MonitorState::Inactive;
+        }
+
+        let since = *self.over_since.get_or_insert_with(Instant::now);
+        if since.elapsed() >= self.sustained_for {
+            MonitorState::Active
+        } else {
+            MonitorState::Inactive
+        }
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn pid(&self) -> Option<usize> {
+        self.last_pid
+    }
+}
@@ -0,0 +1,25 @@
+//! [`Notifier`] that sends events through a Home Assistant `notify` service.
+
+use crate::config::HomeAssistantNotifyConfig;
+use crate::hass;
+
+use super::{Notifier, PowerEvent};
+
+/// Sends each event as a message through Home Assistant's `notify` service.
+pub struct HomeAssistantNotifier {
+    config: HomeAssistantNotifyConfig,
+}
+
+impl HomeAssistantNotifier {
+    /// Create a notifier that sends events through the configured
+    /// `notify.<service>`.
+    pub fn new(config: HomeAssistantNotifyConfig) -> Self {
+        HomeAssistantNotifier { config }
+    }
+}
+
+impl Notifier for HomeAssistantNotifier {
+    fn send(&self, event: PowerEvent) -> Result<(), Box<dyn std::error::Error>> {
+        hass::send_notification(&self.config, &event.message())
+    }
+}
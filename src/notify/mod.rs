@@ -0,0 +1,72 @@
+//! Notification sinks for power state changes, driven by the same monitor
+//! transitions that control a rule's backend.
+
+use std::sync::Arc;
+
+use crate::config::NotifyConfig;
+
+pub mod hass_notify;
+pub mod webhook;
+
+/// A change in a rule's power state worth telling someone about.
+#[derive(Clone, Copy, Debug)]
+pub enum PowerEvent {
+    /// The rule's entity was turned on.
+    TurnedOn { rule_id: usize },
+    /// The rule's entity was turned off.
+    TurnedOff { rule_id: usize },
+    /// The shutoff was skipped because the process restarted within the
+    /// debounce window.
+    ShutoffSkipped { rule_id: usize },
+}
+
+impl PowerEvent {
+    /// A short human-readable message describing this event.
+    pub fn message(&self) -> String {
+        match self {
+            PowerEvent::TurnedOn { rule_id } => format!("Rule {} turned on", rule_id),
+            PowerEvent::TurnedOff { rule_id } => format!("Rule {} turned off", rule_id),
+            PowerEvent::ShutoffSkipped { rule_id } => {
+                format!("Rule {} shutoff skipped, process restarted", rule_id)
+            }
+        }
+    }
+}
+
+/// A sink that [`PowerEvent`]s can be dispatched to.
+pub trait Notifier: Send + Sync {
+    /// Send `event` to this sink.
+    fn send(&self, event: PowerEvent) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// Build the [`Notifier`]s described by the configured `[[notify]]` list.
+pub fn build_notifiers(configs: &[NotifyConfig]) -> Vec<Box<dyn Notifier>> {
+    configs
+        .iter()
+        .map(|config| -> Box<dyn Notifier> {
+            match config {
+                NotifyConfig::Webhook { url } => Box::new(webhook::WebhookNotifier::new(url.clone())),
+                NotifyConfig::HomeAssistant(config) => {
+                    Box::new(hass_notify::HomeAssistantNotifier::new(config.clone()))
+                }
+            }
+        })
+        .collect()
+}
+
+/// Send `event` to every configured notifier, on its own thread so a slow
+/// or unreachable sink can never stall the caller (the shared
+/// event-processing loop that every rule's state changes flow through).
+/// Mirrors [`crate::hooks::spawn_hook`]'s off-threading of external calls.
+/// Failures are logged but never propagated, so a broken sink can never
+/// affect power control.
+pub fn dispatch(notifiers: &Arc<Vec<Box<dyn Notifier>>>, event: PowerEvent) {
+    let notifiers = notifiers.clone();
+    std::thread::spawn(move || {
+        for notifier in notifiers.iter() {
+            if let Err(err) = notifier.send(event) {
+                tracing::warn!(?err, ?event, "Unable to send notification");
+            }
+        }
+    });
+}
@@ -0,0 +1,46 @@
+//! Generic webhook [`Notifier`].
+
+use serde::Serialize;
+
+use super::{Notifier, PowerEvent};
+
+/// Posts each event as JSON to a configured URL.
+pub struct WebhookNotifier {
+    url: String,
+}
+
+/// The body POSTed to a webhook for each event.
+#[derive(Serialize)]
+struct WebhookBody {
+    /// The kind of event, e.g. `"turned_on"`.
+    event: &'static str,
+    /// The rule that triggered the event.
+    rule_id: usize,
+    /// A short human-readable message describing the event.
+    message: String,
+}
+
+impl WebhookNotifier {
+    /// Create a notifier that POSTs each event to `url`.
+    pub fn new(url: String) -> Self {
+        WebhookNotifier { url }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn send(&self, event: PowerEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let (event_name, rule_id) = match event {
+            PowerEvent::TurnedOn { rule_id } => ("turned_on", rule_id),
+            PowerEvent::TurnedOff { rule_id } => ("turned_off", rule_id),
+            PowerEvent::ShutoffSkipped { rule_id } => ("shutoff_skipped", rule_id),
+        };
+
+        ureq::post(&self.url).send_json(serde_json::to_value(WebhookBody {
+            event: event_name,
+            rule_id,
+            message: event.message(),
+        })?)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+//! Retry scheduling with exponential backoff and jitter, so a flaky API
+//! call gets retried instead of crashing the daemon, without hammering a
+//! backend that is still down.
+
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+/// Backoff after the first failure.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Maximum backoff between retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// How much a backoff is randomly adjusted by, to avoid many rules retrying
+/// in lockstep.
+const JITTER: f64 = 0.2;
+
+/// Tracks consecutive failures for a single retried operation. Each failure
+/// doubles the delay before the next attempt is allowed, up to a cap; a
+/// success resets it so the next failure starts from [`INITIAL_BACKOFF`]
+/// again.
+pub struct RetryState {
+    backoff: Option<Duration>,
+    next_attempt: Instant,
+}
+
+impl Default for RetryState {
+    fn default() -> Self {
+        RetryState {
+            backoff: None,
+            next_attempt: Instant::now(),
+        }
+    }
+}
+
+impl RetryState {
+    /// Whether enough time has passed since the last failure to attempt the
+    /// call again.
+    pub fn is_ready(&self) -> bool {
+        Instant::now() >= self.next_attempt
+    }
+
+    /// How long until the next attempt is allowed, or `Duration::ZERO` if
+    /// it already is.
+    pub fn time_until_ready(&self) -> Duration {
+        self.next_attempt.saturating_duration_since(Instant::now())
+    }
+
+    /// Record a successful call, resetting the backoff.
+    pub fn record_success(&mut self) {
+        self.backoff = None;
+        self.next_attempt = Instant::now();
+    }
+
+    /// Record a failed call, doubling the backoff (within ±20% jitter) and
+    /// scheduling the next attempt.
+    pub fn record_failure(&mut self) {
+        let backoff = self
+            .backoff
+            .map(|backoff| (backoff * 2).min(MAX_BACKOFF))
+            .unwrap_or(INITIAL_BACKOFF);
+
+        let jitter = rand::thread_rng().gen_range(-JITTER..=JITTER);
+        self.next_attempt = Instant::now() + backoff.mul_f64(1.0 + jitter);
+        self.backoff = Some(backoff);
+    }
+}